@@ -11,6 +11,17 @@ use std::{cell::RefCell, collections::HashMap};
 type Memory = VirtualMemory<DefaultMemoryImpl>;
 type IdCell = Cell<u64, Memory>;
 
+// Take a full state snapshot every this many appended events
+const KEEP_STATE_EVERY: u64 = 64;
+
+// Upper bounds on variable-length Laundry fields, so a stored/logged record never outgrows
+// Laundry::MAX_SIZE / Event::MAX_SIZE
+const MAX_TAGS: usize = 16;
+const MAX_TAG_LEN: usize = 32;
+const MAX_DEPENDENCIES: usize = 32;
+const MAX_TIME_ENTRIES: usize = 64;
+const MAX_NOTE_LEN: usize = 200;
+
 // Define User struct
 #[derive(candid::CandidType, Serialize, Deserialize, Default, Clone)]
 struct User {
@@ -38,6 +49,23 @@ impl BoundedStorable for User {
     const IS_FIXED_SIZE: bool = false;
 }
 
+// How urgently a laundry should be processed; later variants rank higher and are queued first
+#[derive(candid::CandidType, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+enum Priority {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+// A single logged work session against a laundry
+#[derive(candid::CandidType, Serialize, Deserialize, Default, Clone)]
+struct TimeEntry {
+    started_at: u64,
+    duration: Duration,
+    note: Option<String>,
+}
+
 #[derive(candid::CandidType, Serialize, Deserialize, Default, Clone)]
 struct Laundry {
     id: u64,
@@ -49,6 +77,10 @@ struct Laundry {
     created_at: u64,
     updated_at: Option<u64>,
     finished_at: Option<u64>,
+    priority: Priority,
+    tags: Vec<String>,
+    dependencies: Vec<u64>,
+    time_entries: Vec<TimeEntry>,
 }
 
 // Implement Storable trait for Laundry
@@ -63,7 +95,147 @@ impl Storable for Laundry {
 
 // Implement BoundedStorable trait for Laundry
 impl BoundedStorable for Laundry {
-    const MAX_SIZE: u32 = 1024;
+    // On top of the bounded tags and dependencies, time_entries grows over a laundry's lifetime
+    // (up to MAX_TIME_ENTRIES entries with notes of up to MAX_NOTE_LEN bytes, ~14 KB in all),
+    // so this needs real headroom beyond the baseline fields
+    const MAX_SIZE: u32 = 16384;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// A span of time expressed in whole hours and minutes, used for turnarounds and remaining time
+#[derive(candid::CandidType, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+struct Duration {
+    hours: u16,
+    minutes: u16,
+}
+
+impl Duration {
+    // Break a nanosecond count down into whole hours and minutes
+    fn from_nanos(nanos: u64) -> Self {
+        let hours = nanos / 3_600_000_000_000;
+        let minutes = (nanos - hours * 3_600_000_000_000) / 60_000_000_000;
+        Duration { hours: hours as u16, minutes: minutes as u16 }
+    }
+
+    // Convert back to nanoseconds
+    fn to_nanos(&self) -> u64 {
+        (self.hours as u64 * 60 + self.minutes as u64) * 60_000_000_000
+    }
+}
+
+// A configurable laundry package: its per-kg price and turnaround time
+#[derive(candid::CandidType, Serialize, Deserialize, Default, Clone)]
+struct Package {
+    name: String,
+    price_per_kg: u64,
+    turnaround: Duration,
+}
+
+// Implement Storable trait for Package
+impl Storable for Package {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+// Implement BoundedStorable trait for Package
+impl BoundedStorable for Package {
+    const MAX_SIZE: u32 = 256;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// An immutable record of a single mutating call, used to audit and replay state
+#[derive(candid::CandidType, Serialize, Deserialize, Clone)]
+enum Event {
+    UserCreated {
+        id: u64,
+        name: String,
+        timestamp: u64,
+    },
+    LaundryCreated {
+        id: u64,
+        user_id: u64,
+        weight: u64,
+        package: String,
+        amount_to_pay: u64,
+        priority: Priority,
+        tags: Vec<String>,
+        dependencies: Vec<u64>,
+        timestamp: u64,
+    },
+    LaundryPaid {
+        id: u64,
+        user_id: u64,
+        finished_at: u64,
+        timestamp: u64,
+    },
+    LaundryDone {
+        id: u64,
+        user_id: u64,
+        timestamp: u64,
+    },
+    WorkLogged {
+        id: u64,
+        duration: Duration,
+        note: Option<String>,
+        timestamp: u64,
+    },
+}
+
+impl Event {
+    // The laundry this event touches, if any
+    fn laundry_id(&self) -> Option<u64> {
+        match self {
+            Event::UserCreated { .. } => None,
+            Event::LaundryCreated { id, .. } => Some(*id),
+            Event::LaundryPaid { id, .. } => Some(*id),
+            Event::LaundryDone { id, .. } => Some(*id),
+            Event::WorkLogged { id, .. } => Some(*id),
+        }
+    }
+}
+
+// Implement Storable trait for Event
+impl Storable for Event {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+// Implement BoundedStorable trait for Event
+impl BoundedStorable for Event {
+    // LaundryCreated carries tags/dependencies, which can grow well past a fixed handful of
+    // entries, so this needs more headroom than a flat record would
+    const MAX_SIZE: u32 = 4096;
+    const IS_FIXED_SIZE: bool = false;
+}
+
+// A full copy of the canister's live state, taken every KEEP_STATE_EVERY events
+#[derive(candid::CandidType, Serialize, Deserialize, Default, Clone)]
+struct Snapshot {
+    users: Vec<User>,
+    laundries: Vec<Laundry>,
+}
+
+// Implement Storable trait for Snapshot
+impl Storable for Snapshot {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        Cow::Owned(Encode!(self).unwrap())
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        Decode!(bytes.as_ref(), Self).unwrap()
+    }
+}
+
+// Implement BoundedStorable trait for Snapshot
+impl BoundedStorable for Snapshot {
+    const MAX_SIZE: u32 = 1024 * 1024;
     const IS_FIXED_SIZE: bool = false;
 }
 
@@ -87,6 +259,95 @@ thread_local! {
         RefCell::new(StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(2)))
         ));
+
+    static EVENT_LOG_STORAGE: RefCell<StableBTreeMap<u64, Event, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(3)))
+        ));
+
+    static CHECKPOINT_STORAGE: RefCell<StableBTreeMap<u64, Snapshot, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(4)))
+        ));
+
+    static EVENT_SEQ_COUNTER: RefCell<IdCell> = RefCell::new(
+        IdCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(5))), 0)
+        .expect("cannot create a counter")
+    );
+
+    static PACKAGE_STORAGE: RefCell<StableBTreeMap<String, Package, Memory>> =
+        RefCell::new(StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(6)))
+        ));
+}
+
+// Seed the package catalog with the default offerings on canister install
+#[ic_cdk::init]
+fn init() {
+    seed_default_packages();
+}
+
+// Insert each built-in package whose name is not already in the catalog
+fn seed_default_packages() {
+    let packages = [
+        Package {
+            name: "regular".to_string(),
+            price_per_kg: 6,
+            turnaround: Duration { hours: 24, minutes: 0 },
+        },
+        Package {
+            name: "express".to_string(),
+            price_per_kg: 10,
+            turnaround: Duration { hours: 4, minutes: 0 },
+        },
+    ];
+
+    PACKAGE_STORAGE.with(|service| {
+        let mut service = service.borrow_mut();
+        for package in packages {
+            if service.get(&package.name).is_none() {
+                service.insert(package.name.clone(), package);
+            }
+        }
+    });
+}
+
+// Function to add a new package to the catalog
+#[ic_cdk::update]
+fn add_package(payload: PackagePayload) -> Result<Package, Error> {
+    if PACKAGE_STORAGE.with(|service| service.borrow().get(&payload.name)).is_some() {
+        return Err(Error::InvalidInput { msg: "Package already exists".to_string() });
+    }
+
+    let package = Package {
+        name: payload.name,
+        price_per_kg: payload.price_per_kg,
+        turnaround: payload.turnaround,
+    };
+    PACKAGE_STORAGE.with(|service| service.borrow_mut().insert(package.name.clone(), package.clone()));
+    Ok(package)
+}
+
+// Function to update an existing package's pricing and turnaround
+#[ic_cdk::update]
+fn update_package(payload: PackagePayload) -> Result<Package, Error> {
+    if PACKAGE_STORAGE.with(|service| service.borrow().get(&payload.name)).is_none() {
+        return Err(Error::NotFound { msg: "Package not found".to_string() });
+    }
+
+    let package = Package {
+        name: payload.name,
+        price_per_kg: payload.price_per_kg,
+        turnaround: payload.turnaround,
+    };
+    PACKAGE_STORAGE.with(|service| service.borrow_mut().insert(package.name.clone(), package.clone()));
+    Ok(package)
+}
+
+// Function to list every package in the catalog
+#[ic_cdk::query]
+fn list_packages() -> Vec<Package> {
+    PACKAGE_STORAGE.with(|service| service.borrow().iter().map(|(_, package)| package).collect())
 }
 
 // Payload for creating users
@@ -101,6 +362,9 @@ struct LaundryPayload {
     weight: u64,
     user_id: u64,
     package: String,
+    priority: Priority,
+    tags: Vec<String>,
+    dependencies: Vec<u64>,
 }
 
 // Payload for paying for laundry
@@ -110,6 +374,14 @@ struct PayPayload {
     laundry_id: u64,
 }
 
+// Payload for creating or updating a package in the catalog
+#[derive(candid::CandidType, Serialize, Deserialize, Default)]
+struct PackagePayload {
+    name: String,
+    price_per_kg: u64,
+    turnaround: Duration,
+}
+
 // Enum for error handling
 #[derive(candid::CandidType, Deserialize, Serialize)]
 enum Error {
@@ -117,8 +389,9 @@ enum Error {
     InvalidInput { msg: String },
     InsufficientBalance { msg: String },
     AlreadyPaid { msg: String },
-    LaundryNotDone { msg: String, remaining_time: (u64, u64) }, // Include remaining time
+    LaundryNotDone { msg: String, remaining_time: Duration },
     LaundryAlreadyDone { msg: String },
+    DependencyNotMet { msg: String, blocking: Vec<u64> },
 }
 
 // Function to add a user
@@ -143,6 +416,11 @@ fn add_user(payload: UserPayload) -> Option<User> {
 
     // Insert user into storage
     do_insert_user(&user);
+    append_event(Event::UserCreated {
+        id: user.id,
+        name: user.name.clone(),
+        timestamp: time(),
+    });
     Some(user)
 }
 
@@ -193,11 +471,29 @@ fn add_laundry(payload: LaundryPayload) -> Result<Laundry, Error> {
     })
     .expect("cannot increment id counter");
 
-    // Calculate amount to pay based on package type
-    let amount_to_pay: u64 = match payload.package.as_str() {
-        "regular" => payload.weight * 6,
-        "express" => payload.weight * 10,
-        _ => return Err(Error::InvalidInput { msg: "Invalid package type".to_string()});
+    // Calculate amount to pay by looking the package up in the catalog
+    let package = match PACKAGE_STORAGE.with(|service| service.borrow().get(&payload.package)) {
+        Some(package) => package,
+        None => return Err(Error::InvalidInput { msg: "Invalid package type".to_string() }),
+    };
+    let amount_to_pay: u64 = payload.weight * package.price_per_kg;
+
+    // Bound tags/dependencies so a stored Laundry (and its LaundryCreated event) never
+    // outgrows Laundry::MAX_SIZE / Event::MAX_SIZE
+    if payload.tags.len() > MAX_TAGS {
+        return Err(Error::InvalidInput { msg: format!("Too many tags, max is {}", MAX_TAGS) });
+    }
+    if payload.tags.iter().any(|tag| tag.len() > MAX_TAG_LEN) {
+        return Err(Error::InvalidInput { msg: format!("Tag is too long, max is {} bytes", MAX_TAG_LEN) });
+    }
+    if payload.dependencies.len() > MAX_DEPENDENCIES {
+        return Err(Error::InvalidInput { msg: format!("Too many dependencies, max is {}", MAX_DEPENDENCIES) });
+    }
+
+    // Reject dependency lists that would turn the dependency graph into a cycle
+    if has_dependency_cycle(id, &payload.dependencies) {
+        return Err(Error::InvalidInput { msg: "Dependency list would create a cycle".to_string() });
+    }
 
     // Create new laundry
     let laundry = Laundry {
@@ -210,6 +506,10 @@ fn add_laundry(payload: LaundryPayload) -> Result<Laundry, Error> {
         created_at: ic_cdk::api::time(),
         updated_at: None,
         finished_at: None,
+        priority: payload.priority,
+        tags: payload.tags,
+        dependencies: payload.dependencies,
+        time_entries: vec![],
     };
 
     // Insert laundry into storage
@@ -220,7 +520,6 @@ fn add_laundry(payload: LaundryPayload) -> Result<Laundry, Error> {
         Some(mut user) => {
             user.pending_orders.push(laundry.id);
             do_insert_user(&user);
-            Ok(laundry)
         }
         None => {
             // Create new user
@@ -233,9 +532,30 @@ fn add_laundry(payload: LaundryPayload) -> Result<Laundry, Error> {
                 completed_orders: vec![],
             };
             do_insert_user(&new_user);
-            Ok(laundry)
         }
     }
+
+    // Append the event only after every storage mutation for this call has landed, so a
+    // checkpoint taken at this seq (e.g. the very first event) reflects the new user too
+    append_event(Event::LaundryCreated {
+        id: laundry.id,
+        user_id: laundry.user_id,
+        weight: laundry.weight,
+        package: laundry.package.clone(),
+        amount_to_pay: laundry.amount_to_pay,
+        priority: laundry.priority,
+        tags: laundry.tags.clone(),
+        dependencies: laundry.dependencies.clone(),
+        timestamp: laundry.created_at,
+    });
+
+    Ok(laundry)
+}
+
+// Function to add many laundries in a single call, one outcome per input in order
+#[ic_cdk::update]
+fn add_laundries_batch(payloads: Vec<LaundryPayload>) -> Vec<Result<Laundry, Error>> {
+    payloads.into_iter().map(add_laundry).collect()
 }
 
 // Function to insert a laundry into storage
@@ -275,6 +595,61 @@ fn get_laundry(id: &u64) -> Option<Laundry> {
     LAUNDRY_STORAGE.with(|service| service.borrow().get(id))
 }
 
+// Walk the dependency chain starting from `dependencies`, following each laundry's own
+// dependencies, to see whether it ever leads back to `new_id` (which would be a cycle)
+fn has_dependency_cycle(new_id: u64, dependencies: &[u64]) -> bool {
+    let mut visited: std::collections::HashSet<u64> = std::collections::HashSet::new();
+    let mut stack: Vec<u64> = dependencies.to_vec();
+
+    while let Some(current) = stack.pop() {
+        if current == new_id {
+            return true;
+        }
+        if visited.insert(current) {
+            if let Some(laundry) = get_laundry(&current) {
+                stack.extend(laundry.dependencies);
+            }
+        }
+    }
+
+    false
+}
+
+// Function to retrieve laundries carrying a given tag
+#[ic_cdk::query]
+fn get_laundries_by_tag(tag: String) -> Vec<Laundry> {
+    LAUNDRY_STORAGE.with(|service| {
+        service
+            .borrow()
+            .iter()
+            .map(|(_, laundry)| laundry)
+            .filter(|laundry| laundry.tags.contains(&tag))
+            .collect()
+    })
+}
+
+// Function to retrieve active orders sorted by priority (highest first), then by creation time
+#[ic_cdk::query]
+fn get_pending_queue() -> Vec<Laundry> {
+    let mut queue: Vec<Laundry> = LAUNDRY_STORAGE.with(|service| {
+        service
+            .borrow()
+            .iter()
+            .map(|(_, laundry)| laundry)
+            .filter(|laundry| laundry.status == "paid/on progress")
+            .collect()
+    });
+
+    queue.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.created_at.cmp(&b.created_at)));
+    queue
+}
+
+// Function to retrieve many laundries by ID in a single call, one outcome per input in order
+#[ic_cdk::query]
+fn get_laundries_by_ids(ids: Vec<u64>) -> Vec<Result<Laundry, Error>> {
+    ids.into_iter().map(get_laundry_by_id).collect()
+}
+
 // Function to pay for a laundry
 #[ic_cdk::update]
 fn pay_laundry(payload: PayPayload) -> Result<Laundry, Error> {
@@ -287,6 +662,12 @@ fn pay_laundry(payload: PayPayload) -> Result<Laundry, Error> {
                 None => return Err(Error::NotFound { msg: "Laundry not found".to_string() }),
             };
 
+            // Reject an already-paid laundry before mutating the user's balance/orders, so a
+            // duplicate pay_laundry call can't double-charge or double-record the order
+            if laundry.status == "paid/on progress".to_string() || laundry.status == "paid/done".to_string() {
+                return Err(Error::AlreadyPaid { msg: "Laundry already paid".to_string() });
+            }
+
             // Check if user has sufficient balance
             if user.balance < laundry.amount_to_pay {
                 return Err(Error::InsufficientBalance { msg: "Insufficient balance".to_string() });
@@ -306,22 +687,22 @@ fn pay_laundry(payload: PayPayload) -> Result<Laundry, Error> {
             // Update laundry status and timestamps
             match LAUNDRY_STORAGE.with(|service| service.borrow_mut().get_mut(&payload.laundry_id)) {
                 Some(mut laundry) => {
-                    if laundry.status == "paid/on progress".to_string() || laundry.status == "paid/done".to_string() {
-                        return Err(Error::AlreadyPaid { msg: "Laundry already paid".to_string() });
-                    }
                     laundry.status = "paid/on progress".to_string();
 
-                    let current_timestamp = time();
-                    let regular_time = 86400000000000 + current_timestamp;
-                    let express_time = 14400000000000 + current_timestamp;
-                    let finish: u64 = match laundry.package.as_str() {
-                        "regular" => regular_time,
-                        "express" => express_time,
-                        _ => 0,
+                    let package = match PACKAGE_STORAGE.with(|service| service.borrow().get(&laundry.package)) {
+                        Some(package) => package,
+                        None => return Err(Error::InvalidInput { msg: "Invalid package type".to_string() }),
                     };
+                    let finish: u64 = time() + package.turnaround.to_nanos();
 
                     laundry.finished_at = Some(finish);
                     laundry.updated_at = Some(time());
+                    append_event(Event::LaundryPaid {
+                        id: laundry.id,
+                        user_id: laundry.user_id,
+                        finished_at: finish,
+                        timestamp: laundry.updated_at.unwrap(),
+                    });
                     Ok(laundry.clone())
                 }
                 None => Err(Error::NotFound { msg: "Laundry not found".to_string() })
@@ -331,6 +712,12 @@ fn pay_laundry(payload: PayPayload) -> Result<Laundry, Error> {
     }
 }
 
+// Function to pay for many laundries in a single call, one outcome per input in order
+#[ic_cdk::update]
+fn pay_laundries_batch(payloads: Vec<PayPayload>) -> Vec<Result<Laundry, Error>> {
+    payloads.into_iter().map(pay_laundry).collect()
+}
+
 // Function to check if a laundry is done
 #[ic_cdk::update]
 fn is_laundry_done(id: u64) -> Result<Laundry, Error> {
@@ -344,6 +731,20 @@ fn is_laundry_done(id: u64) -> Result<Laundry, Error> {
                 });
             }
 
+            // Check that every dependency has reached paid/done before this one can complete
+            let blocking: Vec<u64> = laundry
+                .dependencies
+                .iter()
+                .cloned()
+                .filter(|dep_id| get_laundry(dep_id).map(|dep| dep.status != "paid/done").unwrap_or(true))
+                .collect();
+            if !blocking.is_empty() {
+                return Err(Error::DependencyNotMet {
+                    msg: "Laundry has unmet dependencies".to_string(),
+                    blocking,
+                });
+            }
+
             // Check if laundry has a finish time
             if let Some(finish) = laundry.finished_at {
                 if time() > finish {
@@ -366,19 +767,23 @@ fn is_laundry_done(id: u64) -> Result<Laundry, Error> {
                         }
                     }
 
+                    append_event(Event::LaundryDone {
+                        id: laundry.id,
+                        user_id: laundry.user_id,
+                        timestamp: laundry.updated_at.unwrap(),
+                    });
+
                     return Ok(laundry);
                 } else {
                     // Calculate time left for laundry completion
-                    let duration = finish - time();
-                    let hours = duration / 3600000000000;
-                    let minutes = (duration - (hours * 3600000000000)) / 60000000000;
+                    let remaining_time = Duration::from_nanos(finish - time());
                     return Err(Error::LaundryNotDone {
-                        msg: format!("Laundry not done. Time left: {}h {}m ", hours, minutes),
-                        remaining_time: (hours, minutes), // Include remaining time
+                        msg: format!("Laundry not done. Time left: {}h {}m ", remaining_time.hours, remaining_time.minutes),
+                        remaining_time,
                     });
                 }
             } else {
-                
+
                 // Return error if laundry has no finish time
             return Err(Error::InvalidInput {
                     msg: "Laundry has no finish time".to_string(),
@@ -391,5 +796,211 @@ fn is_laundry_done(id: u64) -> Result<Laundry, Error> {
     }
 }
 
+// Function to log a work session against a laundry
+#[ic_cdk::update]
+fn log_work(laundry_id: u64, duration_minutes: u64, note: Option<String>) -> Result<Laundry, Error> {
+    if duration_minutes > u16::MAX as u64 * 60 {
+        return Err(Error::InvalidInput { msg: "Duration is too large".to_string() });
+    }
+    if note.as_ref().map(|n| n.len()).unwrap_or(0) > MAX_NOTE_LEN {
+        return Err(Error::InvalidInput { msg: format!("Note is too long, max is {} bytes", MAX_NOTE_LEN) });
+    }
+
+    match get_laundry(&laundry_id) {
+        Some(mut laundry) => {
+            // Bound time_entries so a heavily-logged Laundry never outgrows Laundry::MAX_SIZE
+            if laundry.time_entries.len() >= MAX_TIME_ENTRIES {
+                return Err(Error::InvalidInput { msg: format!("Too many time entries, max is {}", MAX_TIME_ENTRIES) });
+            }
+
+            let duration = Duration {
+                hours: (duration_minutes / 60) as u16,
+                minutes: (duration_minutes % 60) as u16,
+            };
+            let timestamp = time();
+            laundry.time_entries.push(TimeEntry {
+                started_at: timestamp,
+                duration,
+                note: note.clone(),
+            });
+            laundry.updated_at = Some(timestamp);
+            do_insert_laundry(&laundry);
+            append_event(Event::WorkLogged {
+                id: laundry.id,
+                duration,
+                note,
+                timestamp,
+            });
+            Ok(laundry)
+        }
+        None => Err(Error::NotFound { msg: "Laundry not found".to_string() }),
+    }
+}
+
+// Function to sum the time logged against a laundry
+#[ic_cdk::query]
+fn get_total_worked(laundry_id: u64) -> Duration {
+    let total_minutes: u64 = get_laundry(&laundry_id)
+        .map(|laundry| {
+            laundry
+                .time_entries
+                .iter()
+                .map(|entry| entry.duration.hours as u64 * 60 + entry.duration.minutes as u64)
+                .sum()
+        })
+        .unwrap_or(0);
+
+    Duration {
+        hours: (total_minutes / 60) as u16,
+        minutes: (total_minutes % 60) as u16,
+    }
+}
+
+// Append an event to the log, taking a full checkpoint every KEEP_STATE_EVERY events
+fn append_event(event: Event) -> u64 {
+    let seq = EVENT_SEQ_COUNTER.with(|counter| {
+        let current_value = *counter.borrow().get();
+        counter.borrow_mut().set(current_value + 1)
+    })
+    .expect("cannot increment event sequence counter");
+
+    EVENT_LOG_STORAGE.with(|service| service.borrow_mut().insert(seq, event));
+
+    if seq % KEEP_STATE_EVERY == 0 {
+        take_checkpoint(seq);
+    }
+
+    seq
+}
+
+// Snapshot the full live state and store it under the given sequence number
+fn take_checkpoint(seq: u64) {
+    let users: Vec<User> = USER_STORAGE.with(|service| service.borrow().iter().map(|(_, v)| v.clone()).collect());
+    let laundries: Vec<Laundry> = LAUNDRY_STORAGE.with(|service| service.borrow().iter().map(|(_, v)| v.clone()).collect());
+    CHECKPOINT_STORAGE.with(|service| service.borrow_mut().insert(seq, Snapshot { users, laundries }));
+}
+
+// Apply a single logged event onto an in-memory reconstruction of state
+fn apply_event(users: &mut Vec<User>, laundries: &mut Vec<Laundry>, event: &Event) {
+    match event {
+        Event::UserCreated { id, name, .. } => {
+            users.push(User {
+                id: *id,
+                name: name.clone(),
+                balance: 100000,
+                pending_orders: vec![],
+                active_orders: vec![],
+                completed_orders: vec![],
+            });
+        }
+        Event::LaundryCreated { id, user_id, weight, package, amount_to_pay, priority, tags, dependencies, timestamp } => {
+            laundries.push(Laundry {
+                id: *id,
+                weight: *weight,
+                package: package.clone(),
+                amount_to_pay: *amount_to_pay,
+                status: "waiting for payment".to_string(),
+                user_id: *user_id,
+                created_at: *timestamp,
+                updated_at: None,
+                finished_at: None,
+                priority: *priority,
+                tags: tags.clone(),
+                dependencies: dependencies.clone(),
+                time_entries: vec![],
+            });
+            match users.iter_mut().find(|u| u.id == *user_id) {
+                Some(user) => user.pending_orders.push(*id),
+                None => users.push(User {
+                    id: *user_id,
+                    name: format!("User {}", user_id),
+                    balance: 0,
+                    pending_orders: vec![*id],
+                    active_orders: vec![],
+                    completed_orders: vec![],
+                }),
+            }
+        }
+        Event::LaundryPaid { id, user_id, finished_at, timestamp } => {
+            let amount_to_pay = laundries.iter().find(|l| l.id == *id).map(|l| l.amount_to_pay).unwrap_or(0);
+            if let Some(laundry) = laundries.iter_mut().find(|l| l.id == *id) {
+                laundry.status = "paid/on progress".to_string();
+                laundry.finished_at = Some(*finished_at);
+                laundry.updated_at = Some(*timestamp);
+            }
+            if let Some(user) = users.iter_mut().find(|u| u.id == *user_id) {
+                user.balance -= amount_to_pay;
+                user.pending_orders.retain(|&x| x != *id);
+                user.active_orders.push(*id);
+            }
+        }
+        Event::LaundryDone { id, user_id, timestamp } => {
+            if let Some(laundry) = laundries.iter_mut().find(|l| l.id == *id) {
+                laundry.status = "paid/done".to_string();
+                laundry.updated_at = Some(*timestamp);
+            }
+            if let Some(user) = users.iter_mut().find(|u| u.id == *user_id) {
+                user.active_orders.retain(|&x| x != *id);
+                user.completed_orders.push(*id);
+            }
+        }
+        Event::WorkLogged { id, duration, note, timestamp } => {
+            if let Some(laundry) = laundries.iter_mut().find(|l| l.id == *id) {
+                laundry.time_entries.push(TimeEntry {
+                    started_at: *timestamp,
+                    duration: *duration,
+                    note: note.clone(),
+                });
+                laundry.updated_at = Some(*timestamp);
+            }
+        }
+    }
+}
+
+// Reconstruct (Vec<User>, Vec<Laundry>) as of sequence number `up_to` (or the latest event if None)
+// by loading the most recent checkpoint at or before it and replaying the trailing log on top
+#[ic_cdk::query]
+fn replay_state(up_to: Option<u64>) -> (Vec<User>, Vec<Laundry>) {
+    let latest_seq = EVENT_SEQ_COUNTER.with(|counter| *counter.borrow().get());
+    let target = up_to.unwrap_or(latest_seq);
+
+    let checkpoint = CHECKPOINT_STORAGE.with(|service| {
+        service
+            .borrow()
+            .iter()
+            .filter(|(seq, _)| *seq <= target)
+            .max_by_key(|(seq, _)| *seq)
+            .map(|(seq, snapshot)| (seq, snapshot.clone()))
+    });
+
+    let (mut users, mut laundries, start) = match checkpoint {
+        Some((seq, snapshot)) => (snapshot.users, snapshot.laundries, seq),
+        None => (vec![], vec![], 0),
+    };
+
+    EVENT_LOG_STORAGE.with(|service| {
+        for (seq, event) in service.borrow().iter() {
+            if seq > start && seq <= target {
+                apply_event(&mut users, &mut laundries, &event);
+            }
+        }
+    });
+
+    (users, laundries)
+}
+
+// Function to retrieve the event history for a single laundry
+#[ic_cdk::query]
+fn get_order_history(laundry_id: u64) -> Vec<Event> {
+    EVENT_LOG_STORAGE.with(|service| {
+        service
+            .borrow()
+            .iter()
+            .filter(|(_, event)| event.laundry_id() == Some(laundry_id))
+            .map(|(_, event)| event.clone())
+            .collect()
+    })
+}
+
 // Export Candid functions
 ic_cdk::export_candid!();